@@ -0,0 +1,49 @@
+// Compares fxhash vs xxh3 throughput hashing real station names.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::hash::{BuildHasher, Hasher};
+
+const MEASUREMENTS_FILE: &str = "./data/measurements.txt";
+
+fn station_names() -> Vec<Vec<u8>> {
+    let data = std::fs::read(MEASUREMENTS_FILE).expect("measurements file for benchmarking");
+    data.split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| line.split(|b| *b == b';').next())
+        .map(|s| s.to_vec())
+        .collect()
+}
+
+fn bench_fxhash(c: &mut Criterion) {
+    let names = station_names();
+    let hasher = fxhash::FxBuildHasher::default();
+    c.bench_function("fxhash station names", |b| {
+        b.iter(|| {
+            for name in &names {
+                let mut h = hasher.build_hasher();
+                h.write(black_box(name));
+                black_box(h.finish());
+            }
+        })
+    });
+}
+
+#[cfg(feature = "xxh3")]
+fn bench_xxh3(c: &mut Criterion) {
+    let names = station_names();
+    let hasher = xxhash_rust::xxh3::Xxh3Builder::new();
+    c.bench_function("xxh3 station names", |b| {
+        b.iter(|| {
+            for name in &names {
+                let mut h = hasher.build_hasher();
+                h.write(black_box(name));
+                black_box(h.finish());
+            }
+        })
+    });
+}
+
+#[cfg(feature = "xxh3")]
+criterion_group!(benches, bench_fxhash, bench_xxh3);
+#[cfg(not(feature = "xxh3"))]
+criterion_group!(benches, bench_fxhash);
+criterion_main!(benches);
@@ -0,0 +1,110 @@
+// Compact binary aggregate format, faster to (re)ingest than the JSON output.
+use crate::Measurements;
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+// Non-ASCII first byte plus a CR-LF-1A sequence (as in the PNG signature), so
+// truncated or text-mangled transfers are detected immediately.
+const MAGIC: [u8; 8] = [0x8B, b'B', b'R', b'C', b'\r', b'\n', 0x1A, b'\n'];
+const VERSION: u8 = 1;
+
+// Sorted sequence of records: u16 LE name length, UTF-8 name bytes, then raw
+// i64 min/max/sum and u64 count (scaling is applied by the reader).
+pub fn write_aggregate<'m, W: Write>(
+    w: &mut W,
+    data: impl IntoIterator<Item = (&'m str, &'m Measurements)>,
+) -> io::Result<()> {
+    w.write_all(&MAGIC)?;
+    w.write_all(&[VERSION])?;
+    for (station, m) in data {
+        w.write_all(&(station.len() as u16).to_le_bytes())?;
+        w.write_all(station.as_bytes())?;
+        w.write_all(&m.min.to_le_bytes())?;
+        w.write_all(&m.max.to_le_bytes())?;
+        w.write_all(&m.sum.to_le_bytes())?;
+        w.write_all(&(m.n as u64).to_le_bytes())?;
+    }
+    Ok(())
+}
+
+// Slices bytes[pos..pos+n], advancing pos, or errors instead of panicking.
+fn take<'b>(bytes: &'b [u8], pos: &mut usize, n: usize) -> Result<&'b [u8]> {
+    let end = pos
+        .checked_add(n)
+        .filter(|end| *end <= bytes.len())
+        .ok_or_else(|| anyhow::anyhow!("truncated aggregate file"))?;
+    let slice = &bytes[*pos..end];
+    *pos = end;
+    Ok(slice)
+}
+
+pub fn read_aggregate(bytes: &[u8]) -> Result<BTreeMap<String, Measurements>> {
+    if bytes.len() < MAGIC.len() + 1 || bytes[..MAGIC.len()] != MAGIC {
+        bail!("not a valid aggregate file: magic signature mismatch");
+    }
+    let version = bytes[MAGIC.len()];
+    if version != VERSION {
+        bail!("unsupported aggregate format version {version}, expected {VERSION}");
+    }
+
+    let mut data = BTreeMap::new();
+    let mut pos = MAGIC.len() + 1;
+    while pos < bytes.len() {
+        let name_len = u16::from_le_bytes(take(bytes, &mut pos, 2)?.try_into()?) as usize;
+        let name = std::str::from_utf8(take(bytes, &mut pos, name_len)?)?.to_owned();
+
+        let min = i64::from_le_bytes(take(bytes, &mut pos, 8)?.try_into()?);
+        let max = i64::from_le_bytes(take(bytes, &mut pos, 8)?.try_into()?);
+        let sum = i64::from_le_bytes(take(bytes, &mut pos, 8)?.try_into()?);
+        let n = u64::from_le_bytes(take(bytes, &mut pos, 8)?.try_into()?) as usize;
+
+        data.insert(name, Measurements::from_raw(min, max, sum, n));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_an_aggregate() {
+        let mut data = BTreeMap::new();
+        data.insert(
+            "Hamburg".to_owned(),
+            Measurements::from_raw(-50, 342, 1234, 7),
+        );
+        data.insert("Zurich".to_owned(), Measurements::from_raw(-10, 10, 0, 2));
+
+        let mut buf = Vec::new();
+        write_aggregate(&mut buf, data.iter().map(|(k, v)| (k.as_str(), v))).unwrap();
+        let read_back = read_aggregate(&buf).unwrap();
+
+        assert_eq!(read_back.len(), data.len());
+        for (station, m) in &data {
+            let r = &read_back[station];
+            assert_eq!((r.min, r.max, r.sum, r.n), (m.min, m.max, m.sum, m.n));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let err = read_aggregate(b"not an aggregate file").unwrap_err();
+        assert!(err.to_string().contains("magic"));
+    }
+
+    #[test]
+    fn truncated_record_errors_instead_of_panicking() {
+        let mut data = BTreeMap::new();
+        data.insert(
+            "Hamburg".to_owned(),
+            Measurements::from_raw(-50, 342, 1234, 7),
+        );
+        let mut buf = Vec::new();
+        write_aggregate(&mut buf, data.iter().map(|(k, v)| (k.as_str(), v))).unwrap();
+        buf.truncate(buf.len() - 3);
+
+        assert!(read_aggregate(&buf).is_err());
+    }
+}
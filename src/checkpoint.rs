@@ -0,0 +1,60 @@
+// On-disk checkpoints of partial aggregates, built on binary_format, so long
+// runs can resume or be combined across multiple inputs.
+use crate::binary_format;
+use crate::Measurements;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+// `main`'s OUTPUT_FILE_BIN write already doubles as a checkpoint of the full
+// run; this is for any other checkpoint (e.g. a single worker's partial result).
+pub fn write_checkpoint(path: &Path, data: &BTreeMap<String, Measurements>) -> Result<()> {
+    let mut file = File::create(path)?;
+    binary_format::write_aggregate(&mut file, data.iter().map(|(k, v)| (k.as_str(), v)))?;
+    Ok(())
+}
+
+pub fn merge_checkpoints_into(
+    into: &mut BTreeMap<String, Measurements>,
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<()> {
+    for path in paths {
+        let bytes = std::fs::read(path.as_ref())?;
+        let checkpoint = binary_format::read_aggregate(&bytes)?;
+        for (station, measurements) in checkpoint {
+            into.entry(station)
+                .and_modify(|m| m.merge(measurements))
+                .or_insert(measurements);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merging_checkpoints_matches_merging_in_one_pass() {
+        let mut a = BTreeMap::new();
+        a.insert("Hamburg".to_owned(), Measurements::from_raw(10, 50, 200, 4));
+        let mut b = BTreeMap::new();
+        b.insert("Hamburg".to_owned(), Measurements::from_raw(-20, 30, 100, 3));
+
+        let dir = std::env::temp_dir();
+        let path_a = dir.join("rust_1brc_checkpoint_test_a.bin");
+        let path_b = dir.join("rust_1brc_checkpoint_test_b.bin");
+        write_checkpoint(&path_a, &a).unwrap();
+        write_checkpoint(&path_b, &b).unwrap();
+
+        let mut combined = BTreeMap::new();
+        merge_checkpoints_into(&mut combined, [&path_a, &path_b]).unwrap();
+
+        let hamburg = &combined["Hamburg"];
+        assert_eq!((hamburg.min, hamburg.max, hamburg.sum, hamburg.n), (-20, 50, 300, 7));
+
+        std::fs::remove_file(path_a).unwrap();
+        std::fs::remove_file(path_b).unwrap();
+    }
+}
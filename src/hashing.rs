@@ -0,0 +1,4 @@
+// Alternative station-key hasher selected by the `xxh3` feature.
+#![cfg(feature = "xxh3")]
+
+pub type Xxh3BuildHasher = xxhash_rust::xxh3::Xxh3Builder;
@@ -1,33 +1,50 @@
 #![feature(slice_split_once)]
 
+mod binary_format;
+mod checkpoint;
+mod compression;
+mod hashing;
+mod streaming;
+
 use anyhow::Result;
-use fxhash::FxHashMap;
 use serde::{ser::SerializeStruct, Serialize, Serializer};
 use std::{
     cmp::{max, min},
-    collections::BTreeMap,
+    collections::{BTreeMap, HashMap},
     fs::File,
-    io::Write,
+    hash::BuildHasher,
+    io::{Read, Write},
+    path::Path,
     sync::mpsc::channel,
 };
 
 const MEASUREMENTS_FILE: &str = "./data/measurements.txt";
 const OUTPUT_FILE: &str = "./out/aggregate.json";
+const OUTPUT_FILE_BIN: &str = "./out/aggregate.bin";
 
 const ONE_KIB: usize = 1_024;
 const ONE_MIB: usize = ONE_KIB * 1_024;
 const ONE_GIB: usize = ONE_MIB * 1_024;
 const CHUNK_SIZE: usize = ONE_MIB * 256;
 
+// Readings are stored internally as `value * SCALE_FACTOR`.
+const SCALE_FACTOR: i64 = 10;
+
+// Swapped for xxh3 when the `xxh3` feature is enabled.
+#[cfg(feature = "xxh3")]
+type StationHasher = hashing::Xxh3BuildHasher;
+#[cfg(not(feature = "xxh3"))]
+type StationHasher = fxhash::FxBuildHasher;
+
 #[derive(Debug)]
-struct Data<'a> {
-    m: FxHashMap<&'a [u8], Measurements>,
+struct Data<'a, S = StationHasher> {
+    m: HashMap<&'a [u8], Measurements, S>,
 }
 
-impl<'a> Data<'a> {
+impl<'a, S: BuildHasher + Default> Data<'a, S> {
     fn new() -> Self {
         Self {
-            m: FxHashMap::<&'a [u8], Measurements>::default(),
+            m: HashMap::default(),
         }
     }
 
@@ -38,7 +55,7 @@ impl<'a> Data<'a> {
             .or_insert_with(|| Measurements::new(reading));
     }
 
-    fn merge(&mut self, mut other: Data<'a>) {
+    fn merge(&mut self, mut other: Data<'a, S>) {
         for (station, other) in other.m.drain() {
             self.m
                 .entry(station)
@@ -48,6 +65,19 @@ impl<'a> Data<'a> {
     }
 }
 
+// Parses and aggregates a single newline-delimited chunk, shared by the mmap
+// worker pool and the streaming ingestion path in `streaming`.
+fn process_chunk(chunk: &[u8]) -> Data<'_> {
+    let mut chunk_data = Data::new();
+    for line in chunk.split(|it| *it == b'\n') {
+        if !line.is_empty() {
+            let (station, reading) = parse_line(line);
+            chunk_data.record(station, reading);
+        }
+    }
+    chunk_data
+}
+
 #[derive(Debug, Clone, Copy)]
 struct Measurements {
     min: i64,
@@ -83,6 +113,10 @@ impl Measurements {
     fn avg(&self) -> f64 {
         self.sum as f64 / self.n as f64
     }
+
+    fn from_raw(min: i64, max: i64, sum: i64, n: usize) -> Self {
+        Measurements { min, max, sum, n }
+    }
 }
 impl Serialize for Measurements {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -90,9 +124,9 @@ impl Serialize for Measurements {
         S: Serializer,
     {
         let mut x = serializer.serialize_struct("Measurements", 3)?;
-        x.serialize_field("min", &(self.min as f64 / 10.0))?;
-        x.serialize_field("max", &(self.max as f64 / 10.0))?;
-        x.serialize_field("avg", &(self.avg() / 10.0))?;
+        x.serialize_field("min", &(self.min as f64 / SCALE_FACTOR as f64))?;
+        x.serialize_field("max", &(self.max as f64 / SCALE_FACTOR as f64))?;
+        x.serialize_field("avg", &(self.avg() / SCALE_FACTOR as f64))?;
         x.end()
     }
 }
@@ -101,7 +135,83 @@ impl Serialize for Measurements {
 async fn main() -> Result<()> {
     let start = std::time::Instant::now();
 
-    let file = File::open(MEASUREMENTS_FILE)?;
+    // First positional arg is the input (defaulting to MEASUREMENTS_FILE, "-"
+    // meaning stdin); any further args are checkpoint files to fold in.
+    let mut args = std::env::args().skip(1);
+    let input = args.next().unwrap_or_else(|| MEASUREMENTS_FILE.to_owned());
+    let checkpoint_paths: Vec<String> = args.collect();
+
+    // Compressed input (by extension or magic bytes) always goes through the
+    // streaming decompressor; otherwise regular files get mmapped and anything
+    // else (stdin, a pipe, a socket) goes through the plain streaming path.
+    let mut data = if input == "-" {
+        run_stdin().await?
+    } else {
+        let mut leading_bytes = [0u8; 8];
+        let mut probe = File::open(&input)?;
+        let n = read_full(&mut probe, &mut leading_bytes)?;
+        let codec = compression::Codec::detect(Path::new(&input), &leading_bytes[..n]);
+
+        if let Some(codec) = codec {
+            run_compressed(&input, codec).await?
+        } else if probe.metadata()?.is_file() {
+            run_mmapped(&input)?
+        } else {
+            run_non_mmappable(&input).await?
+        }
+    };
+
+    // CHECKPOINT_OUT, if set, checkpoints this run's own contribution before
+    // folding in any other checkpoints, so it can be combined with others later.
+    if let Ok(checkpoint_out) = std::env::var("CHECKPOINT_OUT") {
+        checkpoint::write_checkpoint(Path::new(&checkpoint_out), &data)?;
+        println!("Wrote checkpoint to {checkpoint_out}");
+    }
+
+    // Fold in previously written checkpoints (if any), so a run can resume
+    // across inputs without rereading what earlier runs already aggregated.
+    if !checkpoint_paths.is_empty() {
+        checkpoint::merge_checkpoints_into(&mut data, &checkpoint_paths)?;
+        println!("Merged {} checkpoint(s)", checkpoint_paths.len());
+    }
+
+    // Write output
+    let start_serializing = std::time::Instant::now();
+    let serialized = serde_json::to_string_pretty(&data)?;
+    let mut out_file = File::create(OUTPUT_FILE)?;
+    out_file.write_all(serialized.as_bytes())?;
+
+    // Doubles as a checkpoint: pass this path back in on a later run to fold
+    // it into that run's results via checkpoint::merge_checkpoints_into.
+    let mut bin_out_file = File::create(OUTPUT_FILE_BIN)?;
+    binary_format::write_aggregate(&mut bin_out_file, data.iter().map(|(k, v)| (k.as_str(), v)))?;
+
+    println!(
+        "Serialized and wrote {} results in {}ms",
+        data.len(),
+        start_serializing.elapsed().as_millis()
+    );
+
+    println!("Completed in {}s", start.elapsed().as_secs_f32());
+    Ok(())
+}
+
+// Reads until `buf` is full or the source hits EOF, unlike a single `read`
+// call which a pipe or socket may satisfy with fewer bytes than requested.
+fn read_full(file: &mut File, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let read = file.read(&mut buf[filled..])?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    Ok(filled)
+}
+
+fn run_mmapped(path: &str) -> Result<BTreeMap<String, Measurements>> {
+    let file = File::open(path)?;
     let mapped_file = unsafe { memmap2::Mmap::map(&file) }?;
     let data: &[u8] = &*mapped_file;
     println!(
@@ -125,14 +235,7 @@ async fn main() -> Result<()> {
         for (chunk_idx, chunk) in chunks.into_iter().enumerate() {
             let sender = sender.clone();
             scope.spawn(async move {
-                let mut chunk_data = Data::new();
-                for line in chunk.split(|it| *it == b'\n') {
-                    if !line.is_empty() {
-                        let (station, reading) = parse_line(line);
-                        chunk_data.record(station, reading);
-                    }
-                }
-                sender.send((chunk_idx, chunk_data)).unwrap();
+                sender.send((chunk_idx, process_chunk(chunk))).unwrap();
             });
         }
         drop(sender);
@@ -146,30 +249,171 @@ async fn main() -> Result<()> {
         start_processing.elapsed().as_secs_f32()
     );
 
-    // Sort
-    let start_sorting = std::time::Instant::now();
-    let mut data = BTreeMap::<&str, Measurements>::new();
+    let mut data = BTreeMap::<String, Measurements>::new();
     for (k, v) in results.m.into_iter() {
-        data.insert(unsafe { std::str::from_utf8_unchecked(k) }, v);
+        data.insert(unsafe { std::str::from_utf8_unchecked(k) }.to_owned(), v);
     }
-    println!(
-        "Sorted results in {}ms",
-        start_sorting.elapsed().as_millis()
-    );
+    Ok(data)
+}
 
-    // Write output
-    let start_serializing = std::time::Instant::now();
-    let serialized = serde_json::to_string_pretty(&data)?;
-    let mut out_file = File::create(OUTPUT_FILE)?;
-    out_file.write_all(serialized.as_bytes())?;
-    println!(
-        "Serialized and wrote {} results in {}ms",
-        data.len(),
-        start_serializing.elapsed().as_millis()
-    );
+#[cfg(feature = "tokio")]
+async fn run_non_mmappable(path: &str) -> Result<BTreeMap<String, Measurements>> {
+    let file = tokio::fs::File::open(path).await?;
+    run_streaming(tokio::io::BufReader::new(file), CHUNK_SIZE).await
+}
 
-    println!("Completed in {}s", start.elapsed().as_secs_f32());
-    Ok(())
+#[cfg(not(feature = "tokio"))]
+async fn run_non_mmappable(path: &str) -> Result<BTreeMap<String, Measurements>> {
+    anyhow::bail!("{path} isn't a regular file; rebuild with --features tokio to stream it")
+}
+
+#[cfg(feature = "tokio")]
+async fn run_compressed(
+    path: &str,
+    codec: compression::Codec,
+) -> Result<BTreeMap<String, Measurements>> {
+    let file = tokio::fs::File::open(path).await?;
+    run_streaming_compressed(tokio::io::BufReader::new(file), codec, CHUNK_SIZE).await
+}
+
+#[cfg(not(feature = "tokio"))]
+async fn run_compressed(
+    path: &str,
+    _codec: compression::Codec,
+) -> Result<BTreeMap<String, Measurements>> {
+    anyhow::bail!("{path} looks compressed; rebuild with --features tokio to decompress it")
+}
+
+// Entry point for `-` (stdin): sniffs the leading bytes for a compression
+// codec without losing them, by splicing them back in front of the stream.
+#[cfg(feature = "tokio")]
+async fn run_stdin() -> Result<BTreeMap<String, Measurements>> {
+    use tokio::io::AsyncReadExt;
+
+    let mut stdin = tokio::io::stdin();
+    let mut leading_bytes = [0u8; 8];
+    let mut filled = 0;
+    while filled < leading_bytes.len() {
+        let read = stdin.read(&mut leading_bytes[filled..]).await?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    let codec = compression::Codec::detect(Path::new("-"), &leading_bytes[..filled]);
+    let reader = tokio::io::BufReader::new(AsyncReadExt::chain(
+        std::io::Cursor::new(leading_bytes[..filled].to_vec()),
+        stdin,
+    ));
+
+    match codec {
+        Some(codec) => run_streaming_compressed(reader, codec, CHUNK_SIZE).await,
+        None => run_streaming(reader, CHUNK_SIZE).await,
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+async fn run_stdin() -> Result<BTreeMap<String, Measurements>> {
+    anyhow::bail!("reading from stdin requires rebuilding with --features tokio")
+}
+
+// Alternative to the mmap ingestion path in `run_mmapped` for sources that can't be
+// mapped (stdin, sockets, pipes, multi-terabyte files): drains `reader`
+// through `streaming::AsyncChunkReader`, folding each chunk's `process_chunk`
+// result into an owned map since chunks don't outlive the loop body here.
+#[cfg(feature = "tokio")]
+async fn run_streaming<R>(reader: R, chunk_size: usize) -> Result<BTreeMap<String, Measurements>>
+where
+    R: tokio::io::AsyncBufRead + Unpin + Send + 'static,
+{
+    use futures_util::StreamExt;
+    use streaming::AsyncChunkReader;
+
+    let mut stream = Box::pin(AsyncChunkReader::new(reader, chunk_size).into_stream());
+    let mut data = BTreeMap::<String, Measurements>::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        for (station, measurements) in process_chunk(&chunk).m {
+            data.entry(unsafe { std::str::from_utf8_unchecked(station) }.to_owned())
+                .and_modify(|m: &mut Measurements| m.merge(measurements))
+                .or_insert(measurements);
+        }
+    }
+    Ok(data)
+}
+
+// Bounded channel capacity between the (sequential) decompression task and the
+// worker pool that consumes its newline-aligned chunks.
+#[cfg(feature = "tokio")]
+const DECODE_CHANNEL_CAPACITY: usize = 4;
+
+// Like run_streaming, but for compressed input: one task drives the decoder
+// and chunk splitter sequentially (decompression can't be parallelized),
+// handing newline-aligned chunks over a bounded channel to a pool of tokio
+// tasks that process_chunk them in parallel and merge into `data`, same as
+// run_mmapped. The pool is plain tokio::spawn rather than run_mmapped's
+// async_scoped::TokioScope, since these chunks are owned and cross an async
+// channel rather than borrowing from an in-memory mmap.
+#[cfg(feature = "tokio")]
+async fn run_streaming_compressed<R>(
+    reader: R,
+    codec: compression::Codec,
+    chunk_size: usize,
+) -> Result<BTreeMap<String, Measurements>>
+where
+    R: tokio::io::AsyncBufRead + Send + Unpin + 'static,
+{
+    use futures_util::StreamExt;
+    use std::sync::Arc;
+    use streaming::AsyncChunkReader;
+    use tokio::sync::Mutex as AsyncMutex;
+
+    let decoded = compression::decoder(codec, reader);
+    let (sender, receiver) = tokio::sync::mpsc::channel::<Vec<u8>>(DECODE_CHANNEL_CAPACITY);
+
+    let decode_task = tokio::spawn(async move {
+        let mut stream = Box::pin(AsyncChunkReader::new(decoded, chunk_size).into_stream());
+        while let Some(chunk) = stream.next().await {
+            if sender.send(chunk?).await.is_err() {
+                break;
+            }
+        }
+        Ok::<_, anyhow::Error>(())
+    });
+
+    let receiver = Arc::new(AsyncMutex::new(receiver));
+    let worker_count = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let workers: Vec<_> = (0..worker_count)
+        .map(|_| {
+            let receiver = Arc::clone(&receiver);
+            tokio::spawn(async move {
+                let mut data = BTreeMap::<String, Measurements>::new();
+                loop {
+                    let chunk = receiver.lock().await.recv().await;
+                    let Some(chunk) = chunk else { break };
+                    for (station, measurements) in process_chunk(&chunk).m {
+                        data.entry(unsafe { std::str::from_utf8_unchecked(station) }.to_owned())
+                            .and_modify(|m: &mut Measurements| m.merge(measurements))
+                            .or_insert(measurements);
+                    }
+                }
+                data
+            })
+        })
+        .collect();
+
+    let mut data = BTreeMap::<String, Measurements>::new();
+    for worker in workers {
+        for (station, measurements) in worker.await? {
+            data.entry(station)
+                .and_modify(|m: &mut Measurements| m.merge(measurements))
+                .or_insert(measurements);
+        }
+    }
+    decode_task.await??;
+    Ok(data)
 }
 
 fn next_chunk(data: &[u8], start: usize, chunk_size: usize) -> &[u8] {
@@ -191,20 +435,40 @@ fn parse_line(line: &[u8]) -> (&[u8], i64) {
 
 #[inline(always)]
 fn parse_reading(reading: &[u8]) -> i64 {
+    let scale_digits = SCALE_FACTOR.ilog10() as usize;
+
+    let mut idx = 0;
     let is_neg = reading[0] == b'-';
-    let len = reading.len();
-    let (d1, d2, d3) = match (is_neg, len) {
-        (false, 3) => (0, reading[0] - b'0', reading[2] - b'0'),
-        (false, 4) => (reading[0] - b'0', reading[1] - b'0', reading[3] - b'0'),
-        (true, 4) => (0, reading[1] - b'0', reading[3] - b'0'),
-        (true, 5) => (reading[1] - b'0', reading[2] - b'0', reading[4] - b'0'),
-        _ => unreachable!(),
+    if is_neg {
+        idx += 1;
+    }
+
+    let mut value: i64 = 0;
+    let mut frac_digits = 0usize;
+    let mut seen_dot = false;
+    while idx < reading.len() {
+        match reading[idx] {
+            b'.' => seen_dot = true,
+            digit => {
+                value = value * 10 + (digit - b'0') as i64;
+                if seen_dot {
+                    frac_digits += 1;
+                }
+            }
+        }
+        idx += 1;
+    }
+
+    let value = match frac_digits.cmp(&scale_digits) {
+        std::cmp::Ordering::Less => value * 10i64.pow((scale_digits - frac_digits) as u32),
+        std::cmp::Ordering::Equal => value,
+        std::cmp::Ordering::Greater => value / 10i64.pow((frac_digits - scale_digits) as u32),
     };
-    let reading = (d1 as i64 * 100) + (d2 as i64 * 10) + (d3 as i64);
+
     if is_neg {
-        -reading
+        -value
     } else {
-        reading
+        value
     }
 }
 
@@ -266,6 +530,21 @@ mod test {
         assert_eq!(reading, 999);
     }
 
+    #[test]
+    fn test_parse_line_arbitrary_precision() {
+        let line = "Station Name;5".as_bytes();
+        let (_, reading) = parse_line(line);
+        assert_eq!(reading, 50);
+
+        let line = "Station Name;100.0".as_bytes();
+        let (_, reading) = parse_line(line);
+        assert_eq!(reading, 1000);
+
+        let line = "Station Name;-12.34".as_bytes();
+        let (_, reading) = parse_line(line);
+        assert_eq!(reading, -123);
+    }
+
     #[test]
     fn test_next_chunk() {
         let data = b"A\nBar\nBaz\n";
@@ -0,0 +1,85 @@
+// Streaming ingestion for sources that cannot be memmap2::Mmap::map'd: stdin,
+// sockets, pipes, and files larger than addressable memory.
+#![cfg(feature = "tokio")]
+
+use futures_core::Stream;
+use tokio::io::{AsyncBufRead, AsyncReadExt};
+
+// Wraps any AsyncRead + AsyncBufRead source and yields owned, newline-terminated
+// chunks, buffering the trailing partial line into the next chunk.
+pub struct AsyncChunkReader<R> {
+    reader: R,
+    chunk_size: usize,
+}
+
+impl<R> AsyncChunkReader<R>
+where
+    R: AsyncBufRead + Unpin + Send,
+{
+    pub fn new(reader: R, chunk_size: usize) -> Self {
+        Self { reader, chunk_size }
+    }
+
+    // Consumes the reader, producing a stream of chunks each ending on a b'\n'
+    // boundary (the final chunk may be unterminated at end of input).
+    pub fn into_stream(self) -> impl Stream<Item = std::io::Result<Vec<u8>>> {
+        let Self {
+            mut reader,
+            chunk_size,
+        } = self;
+        async_stream::try_stream! {
+            let mut pending = Vec::new();
+            loop {
+                let mut buf = vec![0u8; chunk_size];
+                let mut filled = 0;
+                while filled < buf.len() {
+                    let read = reader.read(&mut buf[filled..]).await?;
+                    if read == 0 {
+                        break;
+                    }
+                    filled += read;
+                }
+                if filled == 0 {
+                    if !pending.is_empty() {
+                        yield std::mem::take(&mut pending);
+                    }
+                    break;
+                }
+                buf.truncate(filled);
+
+                let split_at = buf.iter().rposition(|b| *b == b'\n').map(|i| i + 1);
+                match split_at {
+                    Some(split_at) => {
+                        pending.extend_from_slice(&buf[..split_at]);
+                        yield std::mem::replace(&mut pending, buf[split_at..].to_vec());
+                    }
+                    None => pending.extend_from_slice(&buf),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn each_chunk_ends_on_a_newline_boundary() {
+        let input = b"Aachen;12.3\nBerlin;4.5\nCologne;-1.0\n".to_vec();
+        let reader = tokio::io::BufReader::new(&input[..]);
+        let mut stream = Box::pin(AsyncChunkReader::new(reader, 16).into_stream());
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            chunks.push(chunk.unwrap());
+        }
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert_eq!(*chunk.last().unwrap(), b'\n');
+        }
+        assert_eq!(chunks.concat(), input);
+    }
+}